@@ -0,0 +1,313 @@
+//! Persistent storage for tasks, backed by SQLite.
+//!
+//! `DbCtx` wraps a connection pool so the server survives restarts and so
+//! multiple MCP sessions can share one database handle safely.
+
+use std::time::Duration;
+
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::{Connection, OptionalExtension, Row};
+
+use crate::{RunState, Task};
+
+type ConnPool = Pool<SqliteConnectionManager>;
+
+fn row_to_task(row: &Row) -> rusqlite::Result<Task> {
+    let run_state: String = row.get("run_state")?;
+    let run_state = serde_json::from_str(&run_state).map_err(|e| {
+        rusqlite::Error::FromSqlConversionFailure(0, rusqlite::types::Type::Text, Box::new(e))
+    })?;
+    Ok(Task {
+        id: row.get("id")?,
+        title: row.get("title")?,
+        description: row.get("description")?,
+        completed: row.get::<_, i64>("completed")? != 0,
+        run_state,
+    })
+}
+
+/// `RunState` is internally tagged (`#[serde(tag = "status")]`), so it always
+/// round-trips through this helper rather than a hand-written JSON literal —
+/// a bare `"pending"` string doesn't deserialize as `{"status":"pending"}` does.
+fn encode_run_state(state: &RunState) -> serde_json::Result<String> {
+    serde_json::to_string(state)
+}
+
+/// Creates the `tasks` table on a fresh database, or adds columns later
+/// requests introduced (SQLite has no `ADD COLUMN IF NOT EXISTS`).
+fn migrate(conn: &Connection) -> anyhow::Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS tasks (
+            id          INTEGER PRIMARY KEY AUTOINCREMENT,
+            title       TEXT NOT NULL,
+            description TEXT NOT NULL,
+            completed   INTEGER NOT NULL DEFAULT 0
+        )",
+        [],
+    )?;
+
+    let has_run_state = conn
+        .prepare("PRAGMA table_info(tasks)")?
+        .query_map([], |row| row.get::<_, String>(1))?
+        .filter_map(Result::ok)
+        .any(|name| name == "run_state");
+
+    if !has_run_state {
+        let default_state = encode_run_state(&RunState::Draft)?;
+        conn.execute(
+            &format!("ALTER TABLE tasks ADD COLUMN run_state TEXT NOT NULL DEFAULT '{default_state}'"),
+            [],
+        )?;
+    }
+
+    Ok(())
+}
+
+#[derive(Debug)]
+pub struct DbCtx {
+    pool: ConnPool,
+}
+
+impl DbCtx {
+    /// Opens (creating if needed) the SQLite database at `path` and runs migrations.
+    ///
+    /// Every connection in the pool gets a `busy_timeout` and WAL journal mode
+    /// so that concurrent writers (the MCP service's `complete`/`delete` and
+    /// the worker's `claim_next_pending_job`/`finish_job`) block and retry
+    /// under contention instead of failing immediately with `SQLITE_BUSY`.
+    pub fn connect(path: &str) -> anyhow::Result<Self> {
+        let manager = SqliteConnectionManager::file(path).with_init(|conn| {
+            conn.busy_timeout(Duration::from_secs(5))?;
+            conn.pragma_update(None, "journal_mode", "WAL")?;
+            Ok(())
+        });
+        let pool = Pool::new(manager)?;
+
+        let conn = pool.get()?;
+        migrate(&conn)?;
+
+        Ok(Self { pool })
+    }
+
+    /// Inserts a new task and returns it with the ID assigned by SQLite.
+    ///
+    /// New tasks start in `RunState::Draft`, not `Pending`: `claim_next_pending_job`
+    /// only ever claims `Pending` tasks, and `enqueue_job` is what moves a task out
+    /// of `Draft`, so a task isn't runnable the instant it's added.
+    pub async fn insert_task(&self, title: &str, description: &str) -> anyhow::Result<Task> {
+        let pool = self.pool.clone();
+        let title = title.to_string();
+        let description = description.to_string();
+
+        tokio::task::spawn_blocking(move || -> anyhow::Result<Task> {
+            let conn = pool.get()?;
+            let run_state = encode_run_state(&RunState::Draft)?;
+            conn.execute(
+                "INSERT INTO tasks (title, description, completed, run_state) VALUES (?1, ?2, 0, ?3)",
+                rusqlite::params![title, description, run_state],
+            )?;
+            let id = conn.last_insert_rowid();
+
+            Ok(Task {
+                id,
+                title,
+                description,
+                completed: false,
+                run_state: RunState::Draft,
+            })
+        })
+        .await?
+    }
+
+    /// Lists tasks, optionally filtered by completion state, newest-id-last.
+    pub async fn list_tasks(
+        &self,
+        completed: Option<bool>,
+        limit: i64,
+        offset: i64,
+    ) -> anyhow::Result<Vec<Task>> {
+        let pool = self.pool.clone();
+
+        tokio::task::spawn_blocking(move || -> anyhow::Result<Vec<Task>> {
+            let conn = pool.get()?;
+            let (clause, completed) = match completed {
+                Some(c) => ("WHERE completed = ?1", c as i64),
+                None => ("", 0),
+            };
+            let sql = format!(
+                "SELECT id, title, description, completed, run_state FROM tasks {clause} ORDER BY id LIMIT ?2 OFFSET ?3"
+            );
+            let mut stmt = conn.prepare(&sql)?;
+            let rows = stmt.query_map(rusqlite::params![completed, limit, offset], row_to_task)?;
+            rows.collect::<rusqlite::Result<Vec<_>>>()
+                .map_err(Into::into)
+        })
+        .await?
+    }
+
+    /// Fetches a single task by id, if it exists.
+    pub async fn get_task(&self, id: i64) -> anyhow::Result<Option<Task>> {
+        let pool = self.pool.clone();
+
+        tokio::task::spawn_blocking(move || -> anyhow::Result<Option<Task>> {
+            let conn = pool.get()?;
+            conn.query_row(
+                "SELECT id, title, description, completed, run_state FROM tasks WHERE id = ?1",
+                rusqlite::params![id],
+                row_to_task,
+            )
+            .optional()
+            .map_err(Into::into)
+        })
+        .await?
+    }
+
+    /// Marks a task as completed and returns the updated row, if it existed.
+    pub async fn complete_task(&self, id: i64) -> anyhow::Result<Option<Task>> {
+        let pool = self.pool.clone();
+
+        tokio::task::spawn_blocking(move || -> anyhow::Result<Option<Task>> {
+            let conn = pool.get()?;
+            let updated = conn.execute(
+                "UPDATE tasks SET completed = 1 WHERE id = ?1",
+                rusqlite::params![id],
+            )?;
+            if updated == 0 {
+                return Ok(None);
+            }
+            conn.query_row(
+                "SELECT id, title, description, completed, run_state FROM tasks WHERE id = ?1",
+                rusqlite::params![id],
+                row_to_task,
+            )
+            .optional()
+            .map_err(Into::into)
+        })
+        .await?
+    }
+
+    /// Deletes a task by id. Returns whether a row was actually removed.
+    pub async fn delete_task(&self, id: i64) -> anyhow::Result<bool> {
+        let pool = self.pool.clone();
+
+        tokio::task::spawn_blocking(move || -> anyhow::Result<bool> {
+            let conn = pool.get()?;
+            let deleted = conn.execute("DELETE FROM tasks WHERE id = ?1", rusqlite::params![id])?;
+            Ok(deleted > 0)
+        })
+        .await?
+    }
+
+    /// Atomically claims the oldest task still in `Pending` state, flipping it
+    /// to `Running`, or returns `None` if the queue is empty.
+    ///
+    /// This is a single CAS `UPDATE ... RETURNING`, not a `BEGIN`/`SELECT`/
+    /// `UPDATE`/`COMMIT` transaction: a deferred transaction lets two workers
+    /// both `SELECT` the same pending id under their own read snapshot, so in
+    /// WAL mode the loser's `UPDATE` fails with `SQLITE_BUSY_SNAPSHOT` — which
+    /// `busy_timeout` does not retry — instead of just claiming nothing.
+    pub async fn claim_next_pending_job(&self) -> anyhow::Result<Option<Task>> {
+        let pool = self.pool.clone();
+
+        tokio::task::spawn_blocking(move || -> anyhow::Result<Option<Task>> {
+            let conn = pool.get()?;
+            let pending_state = encode_run_state(&RunState::Pending)?;
+            let running_state = encode_run_state(&RunState::Running)?;
+
+            conn.query_row(
+                "UPDATE tasks SET run_state = ?1
+                 WHERE id = (SELECT id FROM tasks WHERE run_state = ?2 ORDER BY id LIMIT 1)
+                   AND run_state = ?2
+                 RETURNING id, title, description, completed, run_state",
+                rusqlite::params![running_state, pending_state],
+                row_to_task,
+            )
+            .optional()
+            .map_err(Into::into)
+        })
+        .await?
+    }
+
+    /// Resets a task to `Pending`, (re-)enqueueing it for a worker to claim.
+    pub async fn enqueue_job(&self, id: i64) -> anyhow::Result<Option<Task>> {
+        let pool = self.pool.clone();
+
+        tokio::task::spawn_blocking(move || -> anyhow::Result<Option<Task>> {
+            let conn = pool.get()?;
+            let run_state = encode_run_state(&RunState::Pending)?;
+            let updated = conn.execute(
+                "UPDATE tasks SET run_state = ?1 WHERE id = ?2",
+                rusqlite::params![run_state, id],
+            )?;
+            if updated == 0 {
+                return Ok(None);
+            }
+            conn.query_row(
+                "SELECT id, title, description, completed, run_state FROM tasks WHERE id = ?1",
+                rusqlite::params![id],
+                row_to_task,
+            )
+            .optional()
+            .map_err(Into::into)
+        })
+        .await?
+    }
+
+    /// Records a job's terminal result and returns the updated row, if it existed.
+    pub async fn finish_job(&self, id: i64, result: String) -> anyhow::Result<Option<Task>> {
+        let pool = self.pool.clone();
+
+        tokio::task::spawn_blocking(move || -> anyhow::Result<Option<Task>> {
+            let conn = pool.get()?;
+            let run_state = encode_run_state(&RunState::Finished { result })?;
+            let updated = conn.execute(
+                "UPDATE tasks SET run_state = ?1 WHERE id = ?2",
+                rusqlite::params![run_state, id],
+            )?;
+            if updated == 0 {
+                return Ok(None);
+            }
+            conn.query_row(
+                "SELECT id, title, description, completed, run_state FROM tasks WHERE id = ?1",
+                rusqlite::params![id],
+                row_to_task,
+            )
+            .optional()
+            .map_err(Into::into)
+        })
+        .await?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    /// Each test gets its own SQLite file: a shared in-memory db would need
+    /// `r2d2`'s pool held to a single connection, which would defeat the
+    /// point of a concurrency test.
+    fn temp_db_path() -> std::path::PathBuf {
+        static COUNTER: AtomicU32 = AtomicU32::new(0);
+        let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+        std::env::temp_dir().join(format!("bugprowler-db-test-{}-{n}.db", std::process::id()))
+    }
+
+    #[tokio::test]
+    async fn claim_next_pending_job_is_exclusive_under_concurrent_claimers() {
+        let db = DbCtx::connect(temp_db_path().to_str().unwrap()).unwrap();
+        let task = db.insert_task("echo hi", "").await.unwrap();
+        db.enqueue_job(task.id).await.unwrap();
+
+        let db = std::sync::Arc::new(db);
+        let (a, b) = tokio::join!(db.claim_next_pending_job(), db.claim_next_pending_job());
+
+        let claimed = [a.unwrap(), b.unwrap()];
+        assert_eq!(
+            claimed.iter().filter(|t| t.is_some()).count(),
+            1,
+            "exactly one concurrent claimer should win the task, the other should see the queue as empty"
+        );
+    }
+}
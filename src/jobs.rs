@@ -0,0 +1,232 @@
+//! A small job-dispatch surface: remote worker clients poll for pending
+//! tasks, stream back command output, and upload a terminal result. Runs in
+//! flight are tracked in [`JobRegistry`] until they complete or hit
+//! [`JOB_TIMEOUT`]; a run that times out keeps its task `Running` in the
+//! database, and that task is not handed out to another worker.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant};
+
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::post;
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{oneshot, Mutex};
+
+use crate::db::DbCtx;
+use crate::Task;
+
+/// How long a claimed job stays tracked in [`JobRegistry`] (and can stream
+/// output) before its entry is dropped. The task's run state in the database
+/// is left unchanged when this expires.
+const JOB_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
+/// The subset of a `Task` a worker needs to know what it picked up.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskInfo {
+    pub id: i64,
+    pub title: String,
+    pub description: String,
+}
+
+/// The command a worker should run for a job. Built from the task's title,
+/// split on whitespace (quotes are not interpreted): the first word is the
+/// program, the rest are arguments.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommandInfo {
+    pub program: String,
+    pub args: Vec<String>,
+}
+
+impl CommandInfo {
+    fn from_task(task: &Task) -> Self {
+        let mut words = task.title.split_whitespace();
+        let program = words.next().unwrap_or("true").to_string();
+        let args = words.map(str::to_string).collect();
+        Self { program, args }
+    }
+}
+
+/// What a worker gets back from `POST /next`: the job to run and where to
+/// upload its output and result artifacts.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RequestedJob {
+    pub task: TaskInfo,
+    pub command: CommandInfo,
+    pub artifact_dir: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OutputChunk {
+    pub stream: OutputStream,
+    pub data: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputStream {
+    Stdout,
+    Stderr,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CompleteRequest {
+    pub result: String,
+}
+
+/// Tracking state for one claimed-but-not-yet-complete job.
+struct ActiveRun {
+    artifact_dir: PathBuf,
+    #[allow(dead_code)]
+    started: Instant,
+    done: Mutex<Option<oneshot::Sender<()>>>,
+}
+
+/// Concurrency-safe map of in-flight job runs, keyed by task id.
+///
+/// Entries are held by [`Weak`] references; the strong `Arc` lives inside the
+/// watcher task spawned in `claim_next`, so a run that's finished (or that
+/// timed out) drops its `Arc` and the map entry stops upgrading, after which
+/// output uploads for it are rejected. Which task is claimed next depends only
+/// on run state in the database, not on this map. The now-dead entry itself is swept out
+/// of the map the next time `claim_next` inserts a new one, so distinct task
+/// ids don't pile up as stale slots for the process lifetime.
+pub struct JobRegistry {
+    active: Mutex<HashMap<i64, Weak<ActiveRun>>>,
+    artifacts_root: PathBuf,
+}
+
+impl JobRegistry {
+    pub fn new(artifacts_root: PathBuf) -> Self {
+        Self {
+            active: Mutex::new(HashMap::new()),
+            artifacts_root,
+        }
+    }
+
+    async fn claim_next(&self, db: &DbCtx) -> anyhow::Result<Option<RequestedJob>> {
+        let Some(task) = db.claim_next_pending_job().await? else {
+            return Ok(None);
+        };
+
+        let artifact_dir = self.artifacts_root.join(task.id.to_string());
+        tokio::fs::create_dir_all(&artifact_dir).await?;
+
+        let (done_tx, done_rx) = oneshot::channel();
+        let run = Arc::new(ActiveRun {
+            artifact_dir: artifact_dir.clone(),
+            started: Instant::now(),
+            done: Mutex::new(Some(done_tx)),
+        });
+
+        {
+            let mut active = self.active.lock().await;
+            active.retain(|_, weak| weak.strong_count() > 0);
+            active.insert(task.id, Arc::downgrade(&run));
+        }
+
+        tokio::spawn(async move {
+            let _run = run; // keeps the map entry alive until done or timed out
+            let _ = tokio::time::timeout(JOB_TIMEOUT, done_rx).await;
+        });
+
+        Ok(Some(RequestedJob {
+            task: TaskInfo {
+                id: task.id,
+                title: task.title.clone(),
+                description: task.description.clone(),
+            },
+            command: CommandInfo::from_task(&task),
+            artifact_dir: artifact_dir.to_string_lossy().into_owned(),
+        }))
+    }
+
+    async fn active_run(&self, id: i64) -> Option<Arc<ActiveRun>> {
+        self.active.lock().await.get(&id).and_then(Weak::upgrade)
+    }
+
+    async fn append_output(&self, id: i64, chunk: OutputChunk) -> anyhow::Result<()> {
+        let run = self
+            .active_run(id)
+            .await
+            .ok_or_else(|| anyhow::anyhow!("no active run for task {id}"))?;
+
+        let name = match chunk.stream {
+            OutputStream::Stdout => "stdout.log",
+            OutputStream::Stderr => "stderr.log",
+        };
+        let path = run.artifact_dir.join(name);
+
+        use tokio::io::AsyncWriteExt;
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .await?;
+        file.write_all(chunk.data.as_bytes()).await?;
+
+        Ok(())
+    }
+
+    async fn mark_done(&self, id: i64) {
+        if let Some(run) = self.active_run(id).await {
+            if let Some(sender) = run.done.lock().await.take() {
+                let _ = sender.send(());
+            }
+        }
+    }
+}
+
+#[derive(Clone)]
+struct WorkerState {
+    db: Arc<DbCtx>,
+    registry: Arc<JobRegistry>,
+}
+
+/// Builds the worker-facing axum router (separate from the MCP `/mcp`
+/// surface) that remote job runners poll against.
+pub fn worker_router(db: Arc<DbCtx>, registry: Arc<JobRegistry>) -> Router {
+    Router::new()
+        .route("/next", post(next_job))
+        .route("/{id}/output", post(submit_output))
+        .route("/{id}/complete", post(complete_job))
+        .with_state(WorkerState { db, registry })
+}
+
+async fn next_job(State(state): State<WorkerState>) -> Response {
+    match state.registry.claim_next(&state.db).await {
+        Ok(Some(job)) => Json(job).into_response(),
+        Ok(None) => StatusCode::NO_CONTENT.into_response(),
+        Err(err) => (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response(),
+    }
+}
+
+async fn submit_output(
+    State(state): State<WorkerState>,
+    Path(id): Path<i64>,
+    Json(chunk): Json<OutputChunk>,
+) -> Response {
+    match state.registry.append_output(id, chunk).await {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(err) => (StatusCode::NOT_FOUND, err.to_string()).into_response(),
+    }
+}
+
+async fn complete_job(
+    State(state): State<WorkerState>,
+    Path(id): Path<i64>,
+    Json(req): Json<CompleteRequest>,
+) -> Response {
+    match state.db.finish_job(id, req.result).await {
+        Ok(Some(task)) => {
+            state.registry.mark_done(id).await;
+            Json(task).into_response()
+        }
+        Ok(None) => StatusCode::NOT_FOUND.into_response(),
+        Err(err) => (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response(),
+    }
+}
@@ -1,5 +1,14 @@
-use rmcp::handler::server::tool::ToolRouter;
-use rmcp::handler::server::wrapper::Parameters;
+mod db;
+mod jobs;
+mod origin_store;
+mod store;
+mod telemetry;
+
+use db::DbCtx;
+use futures::future::{BoxFuture, FutureExt, Shared};
+use jobs::JobRegistry;
+use origin_store::OriginStore;
+use rmcp::handler::server::tool::{Parameters, ToolRouter};
 use rmcp::model::{
     CallToolResult, Content, ErrorData as McpError, Implementation, ProtocolVersion,
     ServerCapabilities, ServerInfo,
@@ -8,32 +17,79 @@ use rmcp::transport::StreamableHttpService;
 use rmcp::transport::streamable_http_server::session::local::LocalSessionManager;
 use rmcp::{ServerHandler, tool, tool_handler, tool_router};
 use serde::{Deserialize, Serialize};
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Weak};
+use store::{StoreConfig, TaskStore};
+use telemetry::{TraceContext, Tracer};
 use tokio::sync::Mutex;
 use tracing_subscriber::prelude::__tracing_subscriber_SubscriberExt;
 use tracing_subscriber::util::SubscriberInitExt;
 
+/// Result of a (possibly in-flight) `add_task` insert, shared across coalesced callers.
+type AddResult = Result<Task, Arc<anyhow::Error>>;
+type AddFuture = Shared<BoxFuture<'static, AddResult>>;
+
+/// A task's position in the job-runner lifecycle, independent of `completed`
+/// (which just tracks the user-facing checkbox).
+///
+/// A freshly added task starts in `Draft`, not `Pending`: `claim_next_pending_job`
+/// only ever claims `Pending` tasks, so without this distinction every task
+/// would become a runnable job (and have its title's first word executed as a
+/// command, see `CommandInfo::from_task`) the instant it was added, making
+/// `enqueue_job` a no-op. `enqueue_job` is what actually moves a task to `Pending`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+enum RunState {
+    #[default]
+    Draft,
+    Pending,
+    Running,
+    Finished { result: String },
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct Task {
-    id: usize,
+    id: i64,
     title: String,
     description: String,
     completed: bool,
+    /// Defaulted so an origin server that doesn't speak the job-runner
+    /// concept (jobs are gated to local-store mode, see `jobs_available`)
+    /// isn't forced to include this field in its task responses.
+    #[serde(default)]
+    run_state: RunState,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 struct TaskManager {
-    tasks: Arc<Mutex<Vec<Task>>>,
-    next_id: Arc<Mutex<usize>>, // Counter for generating unique task IDs
+    /// The task CRUD backend for this replica: local SQLite or a shared HTTP
+    /// origin, picked at startup by `StoreConfig`.
+    store: Arc<dyn TaskStore>,
+    /// The local database, used for the job-dispatch tools below; job runs
+    /// are always claimed from this replica's own SQLite instance.
+    db: Arc<DbCtx>,
+    /// Whether `store` is the local `db` (vs. a shared `OriginStore`). The
+    /// job-dispatch tools only ever see tasks in the local SQLite instance,
+    /// so they're disabled in origin mode rather than silently 404ing on
+    /// every task that was actually added at the origin.
+    jobs_available: bool,
+    /// In-flight `add_task` calls keyed by idempotency key, so concurrent
+    /// retries of the same logical add coalesce onto one insert.
+    pending_adds: Arc<Mutex<HashMap<String, Weak<AddFuture>>>>,
+    tracer: Tracer,
     tool_router: ToolRouter<TaskManager>,
 }
 
 #[tool_router]
 impl TaskManager {
-    fn new() -> Self {
+    fn new(store: Arc<dyn TaskStore>, db: Arc<DbCtx>, jobs_available: bool, tracer: Tracer) -> Self {
         Self {
-            tasks: Arc::new(Mutex::new(Vec::new())),
-            next_id: Arc::new(Mutex::new(1)),
+            store,
+            db,
+            jobs_available,
+            pending_adds: Arc::new(Mutex::new(HashMap::new())),
+            tracer,
             tool_router: Self::tool_router(),
         }
     }
@@ -41,20 +97,32 @@ impl TaskManager {
     #[tool(description = "Add a new task to the task manager")]
     async fn add_task(
         &self,
-        Parameters(AddTaskRequest { title, description }): Parameters<AddTaskRequest>,
+        Parameters(AddTaskRequest {
+            title,
+            description,
+            idempotency_key,
+            trace_parent,
+        }): Parameters<AddTaskRequest>,
     ) -> Result<CallToolResult, McpError> {
-        let mut tasks = self.tasks.lock().await;
-        let mut next_id = self.next_id.lock().await;
+        let mut span =
+            self.tracer
+                .start_tool_span("add_task", None, trace_parent.as_deref().and_then(TraceContext::parse));
 
-        let task = Task {
-            id: *next_id,
-            title: title.clone(),
-            description,
-            completed: false,
-        };
+        let result = match idempotency_key {
+            Some(key) => self.add_task_coalesced(key, title.clone(), description).await,
+            None => self.store.add(title.clone(), description).await.map_err(Arc::new),
+        }
+        .map_err(|e| McpError::internal_error(e.to_string(), None));
 
-        *next_id += 1;
-        tasks.push(task.clone());
+        let task = match result {
+            Ok(task) => task,
+            Err(e) => {
+                span.finish(Err(e.message.to_string()));
+                return Err(e);
+            }
+        };
+        span.set_task_id(task.id);
+        span.finish(Ok(()));
 
         let response = serde_json::json!({
             "success": true,
@@ -66,6 +134,242 @@ impl TaskManager {
             serde_json::to_string_pretty(&response).unwrap(),
         )]))
     }
+
+    /// Single-flights concurrent `add_task` calls that share `key`: the first
+    /// caller drives the insert, later callers await its (cloned) result
+    /// instead of inserting again. Once every `Arc<AddFuture>` clone is
+    /// dropped the `Weak` stops upgrading, so a later add with the same key
+    /// starts a fresh insert; we sweep those dead entries out here so the map
+    /// doesn't grow one stale slot per distinct key for the process lifetime.
+    async fn add_task_coalesced(
+        &self,
+        key: String,
+        title: String,
+        description: String,
+    ) -> AddResult {
+        let mut pending = self.pending_adds.lock().await;
+
+        if let Some(shared) = pending.get(&key).and_then(Weak::upgrade) {
+            drop(pending);
+            return (*shared).clone().await;
+        }
+
+        pending.retain(|_, weak| weak.strong_count() > 0);
+
+        let store = self.store.clone();
+        let fut: BoxFuture<'static, AddResult> =
+            async move { store.add(title, description).await.map_err(Arc::new) }.boxed();
+        let shared: Arc<AddFuture> = Arc::new(fut.shared());
+        pending.insert(key, Arc::downgrade(&shared));
+        drop(pending);
+
+        // Every waiter, including us, clones the same `Shared` future, so a
+        // leader's error (or successful task) is delivered to all of them.
+        (*shared).clone().await
+    }
+
+    /// Rejects job-dispatch tools up front when `store` is an `OriginStore`:
+    /// job runs are only ever claimed from this replica's local SQLite
+    /// instance, so a task added at the origin has no local row to enqueue
+    /// or poll, and would otherwise 404 as if it didn't exist at all.
+    fn require_jobs_available(&self) -> Result<(), McpError> {
+        if self.jobs_available {
+            Ok(())
+        } else {
+            Err(McpError::invalid_request(
+                "job dispatch is unavailable: this server is running with a shared origin store, \
+                 where job runs can't be claimed from a single replica's local database",
+                None,
+            ))
+        }
+    }
+
+    /// Wraps `fut` in an instrumented span for `tool_name`/`task_id`, joining
+    /// `trace_parent`'s trace when the caller supplied one, and reports the
+    /// outcome to the tracer once `fut` resolves.
+    async fn traced<T>(
+        &self,
+        tool_name: &'static str,
+        task_id: Option<i64>,
+        trace_parent: Option<String>,
+        fut: impl Future<Output = Result<T, McpError>>,
+    ) -> Result<T, McpError> {
+        let span = self
+            .tracer
+            .start_tool_span(tool_name, task_id, trace_parent.as_deref().and_then(TraceContext::parse));
+
+        let result = fut.await;
+        match &result {
+            Ok(_) => span.finish(Ok(())),
+            Err(e) => span.finish(Err(e.message.to_string())),
+        }
+        result
+    }
+
+    #[tool(description = "List tasks, optionally filtered by completion state, with pagination")]
+    async fn list_tasks(
+        &self,
+        Parameters(ListTasksRequest {
+            completed,
+            limit,
+            offset,
+            trace_parent,
+        }): Parameters<ListTasksRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        self.traced("list_tasks", None, trace_parent, async {
+            let limit = limit.unwrap_or(50);
+            let offset = offset.unwrap_or(0);
+
+            let tasks = self
+                .store
+                .list(completed, limit, offset)
+                .await
+                .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+
+            let response = serde_json::json!({
+                "success": true,
+                "tasks": tasks,
+                "count": tasks.len(),
+            });
+
+            Ok(CallToolResult::success(vec![Content::text(
+                serde_json::to_string_pretty(&response).unwrap(),
+            )]))
+        })
+        .await
+    }
+
+    #[tool(description = "Retrieve a single task by ID")]
+    async fn get_task(
+        &self,
+        Parameters(TaskIdRequest { id, trace_parent }): Parameters<TaskIdRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        self.traced("get_task", Some(id), trace_parent, async {
+            let task = self
+                .store
+                .get(id)
+                .await
+                .map_err(|e| McpError::internal_error(e.to_string(), None))?
+                .ok_or_else(|| McpError::resource_not_found(format!("task {id} not found"), None))?;
+
+            let response = serde_json::json!({ "success": true, "task": task });
+
+            Ok(CallToolResult::success(vec![Content::text(
+                serde_json::to_string_pretty(&response).unwrap(),
+            )]))
+        })
+        .await
+    }
+
+    #[tool(description = "Mark a task as completed")]
+    async fn complete_task(
+        &self,
+        Parameters(TaskIdRequest { id, trace_parent }): Parameters<TaskIdRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        self.traced("complete_task", Some(id), trace_parent, async {
+            let task = self
+                .store
+                .complete(id)
+                .await
+                .map_err(|e| McpError::internal_error(e.to_string(), None))?
+                .ok_or_else(|| McpError::resource_not_found(format!("task {id} not found"), None))?;
+
+            let response = serde_json::json!({
+                "success": true,
+                "task": task,
+                "message": format!("Task {id} marked as completed"),
+            });
+
+            Ok(CallToolResult::success(vec![Content::text(
+                serde_json::to_string_pretty(&response).unwrap(),
+            )]))
+        })
+        .await
+    }
+
+    #[tool(description = "Delete a task by ID")]
+    async fn delete_task(
+        &self,
+        Parameters(TaskIdRequest { id, trace_parent }): Parameters<TaskIdRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        self.traced("delete_task", Some(id), trace_parent, async {
+            let deleted = self
+                .store
+                .delete(id)
+                .await
+                .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+
+            if !deleted {
+                return Err(McpError::resource_not_found(format!("task {id} not found"), None));
+            }
+
+            let response = serde_json::json!({
+                "success": true,
+                "message": format!("Task {id} deleted"),
+            });
+
+            Ok(CallToolResult::success(vec![Content::text(
+                serde_json::to_string_pretty(&response).unwrap(),
+            )]))
+        })
+        .await
+    }
+
+    #[tool(description = "Enqueue a task as a job for a worker to pick up and run")]
+    async fn enqueue_job(
+        &self,
+        Parameters(TaskIdRequest { id, trace_parent }): Parameters<TaskIdRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        self.traced("enqueue_job", Some(id), trace_parent, async {
+            self.require_jobs_available()?;
+
+            let task = self
+                .db
+                .enqueue_job(id)
+                .await
+                .map_err(|e| McpError::internal_error(e.to_string(), None))?
+                .ok_or_else(|| McpError::resource_not_found(format!("task {id} not found"), None))?;
+
+            let response = serde_json::json!({
+                "success": true,
+                "task": task,
+                "message": format!("Task {id} enqueued for a worker"),
+            });
+
+            Ok(CallToolResult::success(vec![Content::text(
+                serde_json::to_string_pretty(&response).unwrap(),
+            )]))
+        })
+        .await
+    }
+
+    #[tool(description = "Poll the run state (draft, pending, running, or finished) of a job")]
+    async fn job_status(
+        &self,
+        Parameters(TaskIdRequest { id, trace_parent }): Parameters<TaskIdRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        self.traced("job_status", Some(id), trace_parent, async {
+            self.require_jobs_available()?;
+
+            let task = self
+                .db
+                .get_task(id)
+                .await
+                .map_err(|e| McpError::internal_error(e.to_string(), None))?
+                .ok_or_else(|| McpError::resource_not_found(format!("task {id} not found"), None))?;
+
+            let response = serde_json::json!({
+                "success": true,
+                "id": task.id,
+                "run_state": task.run_state,
+            });
+
+            Ok(CallToolResult::success(vec![Content::text(
+                serde_json::to_string_pretty(&response).unwrap(),
+            )]))
+        })
+        .await
+    }
 }
 
 #[derive(Debug, Deserialize, schemars::JsonSchema)]
@@ -74,6 +378,30 @@ struct AddTaskRequest {
     title: String,
     #[schemars(description = "A detailed description of the task")]
     description: String,
+    #[schemars(description = "Optional client-supplied key; concurrent calls with the same key share one insert and return the same task (a call made after an earlier one with that key has finished adds a new task)")]
+    idempotency_key: Option<String>,
+    #[schemars(description = "W3C traceparent header to join this call to an existing distributed trace")]
+    trace_parent: Option<String>,
+}
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+struct ListTasksRequest {
+    #[schemars(description = "If set, only return tasks with this completion state")]
+    completed: Option<bool>,
+    #[schemars(description = "Maximum number of tasks to return (default 50)")]
+    limit: Option<i64>,
+    #[schemars(description = "Number of tasks to skip before collecting results (default 0)")]
+    offset: Option<i64>,
+    #[schemars(description = "W3C traceparent header to join this call to an existing distributed trace")]
+    trace_parent: Option<String>,
+}
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+struct TaskIdRequest {
+    #[schemars(description = "The ID of the task")]
+    id: i64,
+    #[schemars(description = "W3C traceparent header to join this call to an existing distributed trace")]
+    trace_parent: Option<String>,
 }
 
 #[tool_handler]
@@ -87,9 +415,6 @@ impl ServerHandler for TaskManager {
             server_info: Implementation {
                 name: "task-manager".to_string(),
                 version: "0.1.0".to_string(),
-                title: None,
-                website_url: None,
-                icons: None,
             },
             instructions: Some(
                 "A task manager MCP server that allows you to add, complete, list, and retrieve tasks with real-time updates."
@@ -109,8 +434,24 @@ async fn main() -> anyhow::Result<()> {
         .with(tracing_subscriber::fmt::layer())
         .init();
 
+    let db = Arc::new(DbCtx::connect("tasks.db")?);
+    let (tracer, reporter) = telemetry::Tracer::start(telemetry::TracingConfig::from_env());
+
+    let store_config = StoreConfig::from_env();
+    let jobs_available = matches!(store_config, StoreConfig::Local);
+    let store: Arc<dyn TaskStore> = match store_config {
+        StoreConfig::Local => db.clone(),
+        StoreConfig::Origin { base_url } => Arc::new(OriginStore::new(base_url)),
+    };
+
+    // The worker router claims/finishes jobs against the same `tasks.db` the
+    // MCP service reads/writes, so it shares this pool rather than opening a
+    // second one: two independent r2d2 pools against one SQLite file just
+    // means twice the connections contending over the same `busy_timeout`.
+    let worker_db = db.clone();
+
     let service = StreamableHttpService::new(
-        || Ok(TaskManager::new()),
+        move || Ok(TaskManager::new(store.clone(), db.clone(), jobs_available, tracer.clone())),
         LocalSessionManager::default().into(),
         Default::default(),
     );
@@ -118,13 +459,58 @@ async fn main() -> anyhow::Result<()> {
     let router = axum::Router::new().nest_service("/mcp", service);
     let tcp_listener = tokio::net::TcpListener::bind("127.0.0.1:8001").await?;
 
+    let job_registry = Arc::new(JobRegistry::new("artifacts".into()));
+    let worker_router = jobs::worker_router(worker_db, job_registry);
+    let worker_listener = tokio::net::TcpListener::bind("127.0.0.1:8002").await?;
+
     tracing::info!("Server ready at http://127.0.0.1:8001/mcp");
+    tracing::info!("Worker endpoint ready at http://127.0.0.1:8002");
 
-    axum::serve(tcp_listener, router)
-        .with_graceful_shutdown(async {
-            tokio::signal::ctrl_c().await.unwrap();
-        })
-        .await?;
+    let mcp_server = axum::serve(tcp_listener, router).with_graceful_shutdown(async {
+        tokio::signal::ctrl_c().await.unwrap();
+    });
+    let worker_server = axum::serve(worker_listener, worker_router).with_graceful_shutdown(async {
+        tokio::signal::ctrl_c().await.unwrap();
+    });
+
+    tokio::try_join!(mcp_server, worker_server)?;
+
+    reporter.shutdown().await;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use telemetry::TracingConfig;
+
+    fn temp_db_path() -> std::path::PathBuf {
+        static COUNTER: std::sync::atomic::AtomicU32 = std::sync::atomic::AtomicU32::new(0);
+        let n = COUNTER.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        std::env::temp_dir().join(format!("bugprowler-main-test-{}-{n}.db", std::process::id()))
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn add_task_coalesced_inserts_once_for_concurrent_racers() {
+        let db = Arc::new(DbCtx::connect(temp_db_path().to_str().unwrap()).unwrap());
+        let (tracer, _reporter) = Tracer::start(TracingConfig::default());
+        let manager = Arc::new(TaskManager::new(db.clone(), db.clone(), true, tracer));
+
+        let racer = |manager: Arc<TaskManager>| {
+            tokio::spawn(async move {
+                manager
+                    .add_task_coalesced("race-key".to_string(), "same task".to_string(), "".to_string())
+                    .await
+            })
+        };
+        let (a, b) = tokio::join!(racer(manager.clone()), racer(manager.clone()));
+
+        let a = a.unwrap().expect("first racer should succeed");
+        let b = b.unwrap().expect("second racer should succeed");
+        assert_eq!(a.id, b.id, "concurrent racers sharing a key should coalesce onto the same task");
+
+        let rows = db.list_tasks(None, 10, 0).await.unwrap();
+        assert_eq!(rows.len(), 1, "coalesced racers should result in exactly one inserted row");
+    }
+}
@@ -0,0 +1,144 @@
+//! HTTP-backed [`TaskStore`] for deployments where several instances of this
+//! server sit behind a load balancer and must share one task namespace.
+//!
+//! `get` is served from a local `arc-swap`ped cache when possible, so a hot
+//! `get` doesn't round-trip to the origin on every call; `list` always queries
+//! the origin and refreshes the cache with the tasks it returns. Writes always
+//! go to the origin first and only update the cache once it has accepted them.
+//!
+//! The cache is this replica's own, so a write made on another replica isn't
+//! reflected in `get` until an entry expires: entries are only served for
+//! [`CACHE_TTL`], after which a `get` re-fetches from the origin.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use arc_swap::ArcSwap;
+use async_trait::async_trait;
+use reqwest::StatusCode;
+
+use crate::store::TaskStore;
+use crate::Task;
+
+/// How long a cached task may be served to another replica's writes before
+/// this replica re-fetches it from the origin.
+const CACHE_TTL: Duration = Duration::from_secs(5);
+
+pub struct OriginStore {
+    client: reqwest::Client,
+    base_url: String,
+    cache: ArcSwap<HashMap<i64, (Task, Instant)>>,
+}
+
+impl OriginStore {
+    pub fn new(base_url: String) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            base_url,
+            cache: ArcSwap::from_pointee(HashMap::new()),
+        }
+    }
+
+    /// Returns the cached task if present and still within `CACHE_TTL`.
+    fn cache_get(&self, id: i64) -> Option<Task> {
+        let (task, cached_at) = self.cache.load().get(&id)?.clone();
+        (cached_at.elapsed() < CACHE_TTL).then_some(task)
+    }
+
+    fn cache_put(&self, task: Task) {
+        let mut map = (**self.cache.load()).clone();
+        map.insert(task.id, (task, Instant::now()));
+        self.cache.store(Arc::new(map));
+    }
+
+    fn cache_evict(&self, id: i64) {
+        let mut map = (**self.cache.load()).clone();
+        map.remove(&id);
+        self.cache.store(Arc::new(map));
+    }
+}
+
+#[async_trait]
+impl TaskStore for OriginStore {
+    async fn add(&self, title: String, description: String) -> anyhow::Result<Task> {
+        let task: Task = self
+            .client
+            .post(format!("{}/tasks", self.base_url))
+            .json(&serde_json::json!({ "title": title, "description": description }))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        self.cache_put(task.clone());
+        Ok(task)
+    }
+
+    async fn get(&self, id: i64) -> anyhow::Result<Option<Task>> {
+        if let Some(task) = self.cache_get(id) {
+            return Ok(Some(task));
+        }
+
+        let resp = self
+            .client
+            .get(format!("{}/tasks/{id}", self.base_url))
+            .send()
+            .await?;
+        if resp.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+
+        let task: Task = resp.error_for_status()?.json().await?;
+        self.cache_put(task.clone());
+        Ok(Some(task))
+    }
+
+    async fn list(&self, completed: Option<bool>, limit: i64, offset: i64) -> anyhow::Result<Vec<Task>> {
+        let mut req = self
+            .client
+            .get(format!("{}/tasks", self.base_url))
+            .query(&[("limit", limit.to_string()), ("offset", offset.to_string())]);
+        if let Some(completed) = completed {
+            req = req.query(&[("completed", completed.to_string())]);
+        }
+
+        let tasks: Vec<Task> = req.send().await?.error_for_status()?.json().await?;
+        for task in &tasks {
+            self.cache_put(task.clone());
+        }
+        Ok(tasks)
+    }
+
+    async fn complete(&self, id: i64) -> anyhow::Result<Option<Task>> {
+        let resp = self
+            .client
+            .post(format!("{}/tasks/{id}/complete", self.base_url))
+            .send()
+            .await?;
+        if resp.status() == StatusCode::NOT_FOUND {
+            self.cache_evict(id);
+            return Ok(None);
+        }
+
+        let task: Task = resp.error_for_status()?.json().await?;
+        self.cache_put(task.clone());
+        Ok(Some(task))
+    }
+
+    async fn delete(&self, id: i64) -> anyhow::Result<bool> {
+        let resp = self
+            .client
+            .delete(format!("{}/tasks/{id}", self.base_url))
+            .send()
+            .await?;
+        self.cache_evict(id);
+        if resp.status() == StatusCode::NOT_FOUND {
+            return Ok(false);
+        }
+
+        resp.error_for_status()?;
+        Ok(true)
+    }
+}
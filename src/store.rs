@@ -0,0 +1,60 @@
+//! Abstracts task CRUD behind a [`TaskStore`] trait so a deployment can pick,
+//! via configuration, between the local SQLite-backed store and an
+//! HTTP-backed origin shared by several horizontally-scaled instances.
+
+use async_trait::async_trait;
+
+use crate::db::DbCtx;
+use crate::Task;
+
+#[async_trait]
+pub trait TaskStore: Send + Sync {
+    async fn add(&self, title: String, description: String) -> anyhow::Result<Task>;
+    async fn get(&self, id: i64) -> anyhow::Result<Option<Task>>;
+    async fn list(&self, completed: Option<bool>, limit: i64, offset: i64) -> anyhow::Result<Vec<Task>>;
+    async fn complete(&self, id: i64) -> anyhow::Result<Option<Task>>;
+    async fn delete(&self, id: i64) -> anyhow::Result<bool>;
+}
+
+#[async_trait]
+impl TaskStore for DbCtx {
+    async fn add(&self, title: String, description: String) -> anyhow::Result<Task> {
+        self.insert_task(&title, &description).await
+    }
+
+    async fn get(&self, id: i64) -> anyhow::Result<Option<Task>> {
+        self.get_task(id).await
+    }
+
+    async fn list(&self, completed: Option<bool>, limit: i64, offset: i64) -> anyhow::Result<Vec<Task>> {
+        self.list_tasks(completed, limit, offset).await
+    }
+
+    async fn complete(&self, id: i64) -> anyhow::Result<Option<Task>> {
+        self.complete_task(id).await
+    }
+
+    async fn delete(&self, id: i64) -> anyhow::Result<bool> {
+        self.delete_task(id).await
+    }
+}
+
+/// Picks which `TaskStore` a replica should use, read from configuration.
+#[derive(Debug, Clone)]
+pub enum StoreConfig {
+    /// Each replica owns its own SQLite database.
+    Local,
+    /// Several replicas share one task namespace via an HTTP origin.
+    Origin { base_url: String },
+}
+
+impl StoreConfig {
+    /// Reads `BUGPROWLER_ORIGIN_URL` from the environment; its presence
+    /// selects the origin-backed store, otherwise replicas stay local.
+    pub fn from_env() -> Self {
+        match std::env::var("BUGPROWLER_ORIGIN_URL") {
+            Ok(base_url) => Self::Origin { base_url },
+            Err(_) => Self::Local,
+        }
+    }
+}
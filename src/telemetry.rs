@@ -0,0 +1,290 @@
+//! Per-tool-call tracing: an instrumented span for every `#[tool]` invocation,
+//! W3C `traceparent` propagation so calls join a caller's distributed trace,
+//! and a background reporter that ships finished spans over gRPC to a
+//! collector serving the `TraceCollector` service in
+//! `proto/trace_collector.proto`, when one is configured.
+
+use std::time::{Duration, Instant};
+
+use tokio::sync::mpsc;
+use tonic::transport::Channel;
+
+mod proto {
+    tonic::include_proto!("bugprowler.trace.v1");
+}
+
+use proto::trace_collector_client::TraceCollectorClient;
+use proto::{Span as ProtoSpan, SpanBatch};
+
+const CHANNEL_CAPACITY: usize = 1024;
+const BATCH_SIZE: usize = 64;
+const FLUSH_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Where (if anywhere) finished spans get exported.
+#[derive(Debug, Clone, Default)]
+pub struct TracingConfig {
+    pub collector_endpoint: Option<String>,
+}
+
+impl TracingConfig {
+    /// Reads `BUGPROWLER_OTLP_ENDPOINT` from the environment; tracing export
+    /// stays disabled (spans are still recorded locally via `tracing`, just
+    /// not shipped anywhere) when it's unset.
+    pub fn from_env() -> Self {
+        Self {
+            collector_endpoint: std::env::var("BUGPROWLER_OTLP_ENDPOINT").ok(),
+        }
+    }
+}
+
+/// An inbound W3C `traceparent` the caller wants this tool call to join,
+/// e.g. `"00-<32 hex trace id>-<16 hex parent span id>-01"`.
+#[derive(Debug, Clone)]
+pub struct TraceContext {
+    pub trace_id: String,
+    pub parent_span_id: String,
+}
+
+impl TraceContext {
+    pub fn parse(traceparent: &str) -> Option<Self> {
+        let mut parts = traceparent.split('-');
+        let _version = parts.next()?;
+        let trace_id = parts.next()?;
+        let parent_span_id = parts.next()?;
+        if trace_id.len() != 32 || parent_span_id.len() != 16 {
+            return None;
+        }
+        Some(Self {
+            trace_id: trace_id.to_string(),
+            parent_span_id: parent_span_id.to_string(),
+        })
+    }
+}
+
+fn random_hex_id(bytes: usize) -> String {
+    use std::collections::hash_map::RandomState;
+    use std::hash::BuildHasher;
+
+    let mut out = String::with_capacity(bytes * 2);
+    let mut state = RandomState::new().hash_one((Instant::now(), std::process::id()));
+    while out.len() < bytes * 2 {
+        state = state.wrapping_mul(6364136223846793005).wrapping_add(1);
+        out.push_str(&format!("{:02x}", (state >> 56) as u8));
+    }
+    out.truncate(bytes * 2);
+    out
+}
+
+struct FinishedSpan {
+    trace_id: String,
+    span_id: String,
+    parent_span_id: Option<String>,
+    tool_name: &'static str,
+    task_id: Option<i64>,
+    start_unix_nanos: u64,
+    duration: Duration,
+    outcome: Result<(), String>,
+}
+
+/// Starts per-tool spans and hands finished ones to the background reporter.
+#[derive(Debug, Clone)]
+pub struct Tracer {
+    sender: mpsc::Sender<FinishedSpan>,
+}
+
+/// A single tool call's span: records it on drop-by-`finish`, not `Drop`, so
+/// the outcome (success or the error message) is always known.
+///
+/// Holds a (non-entered) `tracing::Span` rather than an `EnteredSpan` guard:
+/// the guard isn't `Send`, and rmcp boxes `#[tool]` handlers as `Send`
+/// futures, so a `ToolSpan` carrying one across an `.await` wouldn't compile.
+pub struct ToolSpan {
+    tracer: Tracer,
+    span: tracing::Span,
+    trace_id: String,
+    span_id: String,
+    parent_span_id: Option<String>,
+    tool_name: &'static str,
+    task_id: Option<i64>,
+    started: Instant,
+    start_unix_nanos: u64,
+}
+
+impl ToolSpan {
+    pub fn set_task_id(&mut self, id: i64) {
+        self.task_id = Some(id);
+    }
+
+    /// Records the call's outcome and ships the finished span to the reporter.
+    pub fn finish(self, outcome: Result<(), String>) {
+        let _entered = self.span.enter();
+        if let Err(ref message) = outcome {
+            tracing::warn!(tool = self.tool_name, error = %message, "tool call failed");
+        }
+
+        let span = FinishedSpan {
+            trace_id: self.trace_id,
+            span_id: self.span_id,
+            parent_span_id: self.parent_span_id,
+            tool_name: self.tool_name,
+            task_id: self.task_id,
+            start_unix_nanos: self.start_unix_nanos,
+            duration: self.started.elapsed(),
+            outcome,
+        };
+
+        // Best-effort: a full or closed channel just means this span is dropped.
+        let _ = self.tracer.sender.try_send(span);
+    }
+}
+
+impl Tracer {
+    /// Spawns the background reporter and returns a `Tracer` handle to it.
+    /// Returns the handle plus a shutdown handle for graceful flush.
+    pub fn start(config: TracingConfig) -> (Self, ReporterHandle) {
+        let (sender, receiver) = mpsc::channel(CHANNEL_CAPACITY);
+        let (shutdown_tx, shutdown_rx) = mpsc::channel(1);
+
+        tokio::spawn(run_reporter(config, receiver, shutdown_rx));
+
+        (Self { sender }, ReporterHandle { shutdown_tx })
+    }
+
+    /// Begins an instrumented span for `tool_name`, joining `inbound`'s trace
+    /// if the caller provided one, otherwise starting a new trace.
+    pub fn start_tool_span(
+        &self,
+        tool_name: &'static str,
+        task_id: Option<i64>,
+        inbound: Option<TraceContext>,
+    ) -> ToolSpan {
+        let (trace_id, parent_span_id) = match inbound {
+            Some(ctx) => (ctx.trace_id, Some(ctx.parent_span_id)),
+            None => (random_hex_id(16), None),
+        };
+        let span_id = random_hex_id(8);
+
+        let span = tracing::info_span!(
+            "tool_call",
+            tool = tool_name,
+            task_id,
+            trace_id = %trace_id,
+            span_id = %span_id,
+        );
+
+        ToolSpan {
+            tracer: self.clone(),
+            span,
+            trace_id,
+            span_id,
+            parent_span_id,
+            tool_name,
+            task_id,
+            started: Instant::now(),
+            start_unix_nanos: unix_nanos_now(),
+        }
+    }
+}
+
+fn unix_nanos_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or(0)
+}
+
+/// Lets `main` ask the reporter to flush and stop during graceful shutdown.
+#[derive(Debug, Clone)]
+pub struct ReporterHandle {
+    shutdown_tx: mpsc::Sender<()>,
+}
+
+impl ReporterHandle {
+    pub async fn shutdown(&self) {
+        let _ = self.shutdown_tx.send(()).await;
+        // The reporter drops its end once it has flushed and returned.
+        self.shutdown_tx.closed().await;
+    }
+}
+
+async fn run_reporter(
+    config: TracingConfig,
+    mut receiver: mpsc::Receiver<FinishedSpan>,
+    mut shutdown_rx: mpsc::Receiver<()>,
+) {
+    let mut client = match &config.collector_endpoint {
+        Some(endpoint) => connect(endpoint).await,
+        None => None,
+    };
+
+    let mut batch = Vec::with_capacity(BATCH_SIZE);
+    let mut ticker = tokio::time::interval(FLUSH_INTERVAL);
+
+    loop {
+        tokio::select! {
+            maybe_span = receiver.recv() => {
+                match maybe_span {
+                    Some(span) => {
+                        batch.push(span);
+                        if batch.len() >= BATCH_SIZE {
+                            flush(&mut client, &mut batch).await;
+                        }
+                    }
+                    None => break,
+                }
+            }
+            _ = ticker.tick() => {
+                flush(&mut client, &mut batch).await;
+            }
+            _ = shutdown_rx.recv() => {
+                receiver.close();
+                while let Ok(span) = receiver.try_recv() {
+                    batch.push(span);
+                }
+                flush(&mut client, &mut batch).await;
+                break;
+            }
+        }
+    }
+}
+
+async fn connect(endpoint: &str) -> Option<TraceCollectorClient<Channel>> {
+    match TraceCollectorClient::connect(endpoint.to_string()).await {
+        Ok(client) => Some(client),
+        Err(err) => {
+            tracing::warn!(%endpoint, error = %err, "failed to connect to trace collector");
+            None
+        }
+    }
+}
+
+async fn flush(client: &mut Option<TraceCollectorClient<Channel>>, batch: &mut Vec<FinishedSpan>) {
+    if batch.is_empty() {
+        return;
+    }
+
+    let Some(client) = client.as_mut() else {
+        batch.clear();
+        return;
+    };
+
+    let spans = batch
+        .drain(..)
+        .map(|span| ProtoSpan {
+            trace_id: span.trace_id,
+            span_id: span.span_id,
+            parent_span_id: span.parent_span_id.unwrap_or_default(),
+            tool_name: span.tool_name.to_string(),
+            task_id: span.task_id.unwrap_or_default(),
+            has_task_id: span.task_id.is_some(),
+            start_unix_nanos: span.start_unix_nanos,
+            duration_nanos: span.duration.as_nanos() as u64,
+            ok: span.outcome.is_ok(),
+            error: span.outcome.err().unwrap_or_default(),
+        })
+        .collect();
+
+    if let Err(err) = client.export_spans(SpanBatch { spans }).await {
+        tracing::warn!(error = %err, "failed to export span batch");
+    }
+}